@@ -0,0 +1,35 @@
+//! Build script: render shell completions and a man page from the shared clap
+//! definition in `src/cli.rs` into `OUT_DIR`, the way packagers expect.
+
+use std::path::PathBuf;
+
+use clap_complete::generate_to;
+use clap_complete::shells::{Bash, Fish, PowerShell, Zsh};
+
+#[path = "src/cli.rs"]
+mod cli;
+
+fn main() -> std::io::Result<()> {
+    let outdir = match std::env::var_os("OUT_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => return Ok(()),
+    };
+
+    let mut cmd = cli::build_cli();
+    let name = "idkspot";
+
+    // Completion scripts for each supported shell.
+    generate_to(Bash, &mut cmd, name, &outdir)?;
+    generate_to(Fish, &mut cmd, name, &outdir)?;
+    generate_to(Zsh, &mut cmd, name, &outdir)?;
+    generate_to(PowerShell, &mut cmd, name, &outdir)?;
+
+    // Man page rendered from the same argument metadata.
+    let man = clap_mangen::Man::new(cmd);
+    let mut buffer = Vec::new();
+    man.render(&mut buffer)?;
+    std::fs::write(outdir.join("idkspot.1"), buffer)?;
+
+    println!("cargo:rerun-if-changed=src/cli.rs");
+    Ok(())
+}
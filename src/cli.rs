@@ -0,0 +1,79 @@
+//! Declarative command-line definition shared between the runtime parser in
+//! `main` and the build-time completion/man-page generator in `build.rs`, so
+//! there is a single source of truth for the CLI surface.
+
+use clap::{Arg, Command};
+
+/// Build the `idkspot` command tree: `start` (with the
+/// interface/ssid/password/channel options), `stop` and `status` subcommands,
+/// plus global `--backend` and `--color` options.
+pub fn build_cli() -> Command {
+    Command::new("idkspot")
+        .about("Wi-Fi hotspot manager")
+        .arg(
+            Arg::new("backend")
+                .long("backend")
+                .value_name("BACKEND")
+                .global(true)
+                .help("Force a hotspot backend: create_ap, nmcli, or hostapd"),
+        )
+        .arg(
+            Arg::new("color")
+                .long("color")
+                .value_name("WHEN")
+                .global(true)
+                .value_parser(["auto", "always", "never"])
+                .default_value("auto")
+                .help("Colorize output: auto, always, or never"),
+        )
+        .subcommand(
+            Command::new("start")
+                .about("Start the hotspot")
+                .arg(
+                    Arg::new("interface")
+                        .short('i')
+                        .long("interface")
+                        .value_name("IFACE")
+                        .help("Wireless interface to use"),
+                )
+                .arg(
+                    Arg::new("ssid")
+                        .short('s')
+                        .long("ssid")
+                        .value_name("SSID")
+                        .help("Network name"),
+                )
+                .arg(
+                    Arg::new("password")
+                        .short('p')
+                        .long("password")
+                        .value_name("PASS")
+                        .help("WPA2 passphrase (8+ characters)"),
+                )
+                .arg(
+                    Arg::new("channel")
+                        .short('c')
+                        .long("channel")
+                        .value_name("CHANNEL")
+                        .help("Channel number (omit to use the detected channel)"),
+                ),
+        )
+        .subcommand(Command::new("stop").about("Stop the hotspot"))
+        .subcommand(
+            Command::new("status")
+                .about("Report running hotspots and their connected clients")
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Emit structured JSON instead of a table"),
+                )
+                .arg(
+                    Arg::new("watch")
+                        .short('w')
+                        .long("watch")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Poll and refresh every 2 seconds"),
+                ),
+        )
+}
@@ -1,8 +1,13 @@
+mod cli;
+
 use gtk4::prelude::*;
 use gtk4::{Application, ApplicationWindow, Box as GtkBox, Button, Entry, Label, Orientation, PasswordEntry, gio, ScrolledWindow, ListBox, ListBoxRow, Dialog, ResponseType};
 use libadwaita as adw;
 use regex::Regex;
 use std::cell::RefCell;
+use std::io::{BufRead, BufReader, IsTerminal, Write as IoWrite};
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::net::{UnixListener, UnixStream};
 use std::process::{Command, Stdio, Child};
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -15,10 +20,156 @@ const BLOCKLIST_FILE: &str = "/tmp/idkspot_blocked_macs.txt";
 static SHOW_WINDOW: AtomicBool = AtomicBool::new(true);
 static APP_RUNNING: AtomicBool = AtomicBool::new(true);
 
-// Root helper process - acquired once at startup
-type RootHelper = Arc<Mutex<Option<Child>>>;
+// Root helper process - acquired once at startup. The privileged side is this
+// same binary re-executed under pkexec in `--root-helper` mode; we talk to it
+// over a structured line protocol, never a shell.
+struct RootHelperProc {
+    child: Child,
+    reader: BufReader<std::process::ChildStdout>,
+}
+type RootHelper = Arc<Mutex<Option<RootHelperProc>>>;
+
+/// State shared between the GTK window and the headless control socket so that
+/// both drive the same hotspot through the same `RootHelper` and `running` flag.
+struct ControlState {
+    backend: Arc<dyn SystemBackend>,
+    hotspot: Arc<dyn HotspotBackend>,
+    interface: Mutex<String>,
+    channel: u32,
+    running: AtomicBool,
+}
+
+type SharedControl = Arc<ControlState>;
+
+/// Path of the line-based control socket, following the `$XDG_RUNTIME_DIR`
+/// (falling back to `/run/user/$UID`) convention used by user services.
+fn control_socket_path() -> String {
+    if let Ok(dir) = std::env::var("XDG_RUNTIME_DIR") {
+        if !dir.is_empty() {
+            return format!("{}/idkspot.sock", dir);
+        }
+    }
+    let uid = std::fs::metadata("/proc/self").map(|m| m.uid()).unwrap_or(0);
+    format!("/run/user/{}/idkspot.sock", uid)
+}
+
+/// All external interaction the tool performs — running commands, sending
+/// privileged commands through the root helper, and reading lease/capability
+/// files — goes through this trait so the parsing and state logic can be
+/// exercised against canned output instead of real hardware and root. Mirrors
+/// the production-vs-test platform split (`RealBackend` wires the real system,
+/// `MockBackend` injects fakes).
+trait SystemBackend: Send + Sync {
+    /// Run a command and collect its completed `Output`.
+    fn run(&self, argv: &[&str]) -> std::io::Result<std::process::Output>;
+    /// Send a command to the privileged helper, returning whether it replied `OK`.
+    fn run_root(&self, cmd: &str) -> bool;
+    /// Read a file to a string (lease files, `iw` capability dumps, …).
+    fn read_file(&self, path: &str) -> std::io::Result<String>;
+}
+
+/// Production backend: real `Command` invocations and the persistent
+/// `pkexec` root helper.
+struct RealBackend {
+    root_helper: RootHelper,
+}
+
+impl SystemBackend for RealBackend {
+    fn run(&self, argv: &[&str]) -> std::io::Result<std::process::Output> {
+        let (cmd, rest) = argv
+            .split_first()
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "empty argv"))?;
+        Command::new(cmd).args(rest).output()
+    }
+
+    fn run_root(&self, cmd: &str) -> bool {
+        run_as_root(&self.root_helper, cmd)
+    }
+
+    fn read_file(&self, path: &str) -> std::io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+}
 
 fn main() -> gtk4::glib::ExitCode {
+    // Headless client mode: `idkspot --ctl LIST` connects to the running
+    // instance's control socket, prints the reply and exits without a window.
+    let argv: Vec<String> = std::env::args().collect();
+
+    // Privileged helper modes: invoked as root via pkexec, these never touch
+    // GTK. `--root-helper` is the persistent dispatcher; `--root-oneshot`
+    // performs a single verb and exits (used as a fallback when no persistent
+    // helper is running).
+    if argv.iter().any(|a| a == "--root-helper") {
+        run_root_helper_loop();
+        return gtk4::glib::ExitCode::SUCCESS;
+    }
+    if let Some(pos) = argv.iter().position(|a| a == "--root-oneshot") {
+        let reply = handle_root_verb(&argv[pos + 1..].join(" "));
+        println!("{}", reply);
+        return gtk4::glib::ExitCode::from(if reply.starts_with("OK") { 0 } else { 1 });
+    }
+
+    let color = ColorChoice::from_args(&argv);
+
+    if let Some(pos) = argv.iter().position(|a| a == "--ctl") {
+        // Drop a trailing `--color ...` so it is not sent as part of the verb.
+        let command = argv[pos + 1..]
+            .iter()
+            .take_while(|a| !a.starts_with("--color"))
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ");
+        return gtk4::glib::ExitCode::from(run_ctl_client(&command, color));
+    }
+
+    // `idkspot start/stop ...` headless subcommands, parsed from the shared clap
+    // definition and forwarded to a running instance over the control socket.
+    if matches!(argv.get(1).map(String::as_str), Some("start") | Some("stop")) {
+        let matches = cli::build_cli().get_matches();
+        let command = match matches.subcommand() {
+            Some(("start", m)) => {
+                let ssid = m.get_one::<String>("ssid").cloned().unwrap_or_default();
+                let channel = m.get_one::<String>("channel").cloned().unwrap_or_default();
+                let password = m.get_one::<String>("password").cloned().unwrap_or_default();
+                let start = format!("START {} {} {}", ssid, channel, password);
+                // Select the requested interface first so START uses it instead
+                // of the server-detected default.
+                match m.get_one::<String>("interface") {
+                    Some(iface) if !iface.is_empty() => format!("SETIFACE {}\n{}", iface, start),
+                    _ => start,
+                }
+            }
+            Some(("stop", _)) => "STOP".to_string(),
+            _ => unreachable!("matched start/stop above"),
+        };
+        return gtk4::glib::ExitCode::from(run_ctl_client(&command, color));
+    }
+
+    // `idkspot status [--json] [--watch]` reads the running hotspots directly
+    // (no privileges needed) and prints a table or JSON, optionally polling.
+    if matches!(argv.get(1).map(String::as_str), Some("status")) {
+        let matches = cli::build_cli().get_matches();
+        if let Some(("status", m)) = matches.subcommand() {
+            let json = m.get_flag("json");
+            let watch = m.get_flag("watch");
+            let backend = RealBackend {
+                root_helper: Arc::new(Mutex::new(None)),
+            };
+            if watch {
+                loop {
+                    // Clear screen and move the cursor home before each refresh.
+                    print!("\x1b[2J\x1b[H");
+                    print_status(&backend, json);
+                    let _ = std::io::stdout().flush();
+                    std::thread::sleep(std::time::Duration::from_secs(2));
+                }
+            }
+            print_status(&backend, json);
+        }
+        return gtk4::glib::ExitCode::SUCCESS;
+    }
+
     // Start tray icon in background thread
     std::thread::spawn(|| {
         run_tray_service();
@@ -28,6 +179,32 @@ fn main() -> gtk4::glib::ExitCode {
     // This spawns a persistent root shell that we can send commands to
     let root_helper = acquire_root_helper();
 
+    let backend: Arc<dyn SystemBackend> = Arc::new(RealBackend {
+        root_helper: root_helper.clone(),
+    });
+
+    // Optional `--backend create_ap|nmcli|hostapd` override, otherwise probe.
+    let forced_backend = argv
+        .iter()
+        .position(|a| a == "--backend")
+        .and_then(|pos| argv.get(pos + 1))
+        .map(|s| s.as_str());
+    let hotspot = detect_hotspot_backend(forced_backend);
+
+    // Detect the interface once up front so the control socket can start a
+    // hotspot on the same channel the window would have used.
+    let (interface, frequency, _) = detect_interface(backend.as_ref());
+    let control: SharedControl = Arc::new(ControlState {
+        backend,
+        hotspot,
+        interface: Mutex::new(interface),
+        channel: freq_to_channel(frequency),
+        running: AtomicBool::new(false),
+    });
+
+    // Serve the headless control protocol alongside the GTK window.
+    spawn_control_listener(control.clone());
+
     // Initialize libadwaita
     adw::init().expect("Failed to initialize libadwaita");
 
@@ -37,9 +214,9 @@ fn main() -> gtk4::glib::ExitCode {
         .build();
 
     let window: Rc<RefCell<Option<ApplicationWindow>>> = Rc::new(RefCell::new(None));
-    
+
     let window_clone = window.clone();
-    let root_helper_clone = root_helper.clone();
+    let control_clone = control.clone();
     app.connect_activate(move |app| {
         if let Some(ref win) = *window_clone.borrow() {
             SHOW_WINDOW.store(true, Ordering::SeqCst);
@@ -47,7 +224,7 @@ fn main() -> gtk4::glib::ExitCode {
             win.present();
             return;
         }
-        build_ui(app, window_clone.clone(), root_helper_clone.clone());
+        build_ui(app, window_clone.clone(), control_clone.clone());
     });
 
     app.connect_command_line(move |app, _| {
@@ -60,47 +237,66 @@ fn main() -> gtk4::glib::ExitCode {
     let result = app.run();
     
     APP_RUNNING.store(false, Ordering::SeqCst);
-    
+
     // Cleanup root helper
     if let Ok(mut helper) = root_helper.lock() {
-        if let Some(ref mut child) = *helper {
-            let _ = child.kill();
+        if let Some(proc) = helper.as_mut() {
+            let _ = proc.child.kill();
         }
     }
-    
+
+    // Remove the control socket so the next launch can re-bind.
+    let _ = std::fs::remove_file(control_socket_path());
+
     result
 }
 
-/// Acquire root helper at startup - only asks for password once
+/// Acquire root helper at startup - only asks for password once.
+///
+/// Re-executes this binary under pkexec in `--root-helper` mode: the privileged
+/// side reads one `verb arg` tuple per line and replies `OK`/`ERR`, so no caller
+/// input ever reaches a shell.
 fn acquire_root_helper() -> RootHelper {
     let helper: RootHelper = Arc::new(Mutex::new(None));
-    
-    // Spawn pkexec with a shell that stays open
-    // We'll send iptables commands through stdin
-    if let Ok(child) = Command::new("pkexec")
-        .args(["sh", "-c", "while read cmd; do eval \"$cmd\"; done"])
+
+    let exe = match std::env::current_exe() {
+        Ok(p) => p,
+        Err(_) => return helper,
+    };
+
+    if let Ok(mut child) = Command::new("pkexec")
+        .arg(exe)
+        .arg("--root-helper")
         .stdin(Stdio::piped())
-        .stdout(Stdio::null())
+        .stdout(Stdio::piped())
         .stderr(Stdio::null())
         .spawn()
     {
-        if let Ok(mut h) = helper.lock() {
-            *h = Some(child);
+        if let Some(stdout) = child.stdout.take() {
+            if let Ok(mut h) = helper.lock() {
+                *h = Some(RootHelperProc {
+                    child,
+                    reader: BufReader::new(stdout),
+                });
+            }
         }
     }
-    
+
     helper
 }
 
-/// Send a command to the root helper
+/// Send a `verb arg` tuple to the root helper and return whether it replied
+/// `OK`. The reply replaces the old fire-and-forget `sleep`, so callers can
+/// tell whether the privileged action actually succeeded.
 fn run_as_root(helper: &RootHelper, command: &str) -> bool {
     if let Ok(mut h) = helper.lock() {
-        if let Some(ref mut child) = *h {
-            if let Some(ref mut stdin) = child.stdin {
-                use std::io::Write;
-                if writeln!(stdin, "{}", command).is_ok() {
-                    let _ = stdin.flush(); // IMPORTANT: flush the command
-                    return true;
+        if let Some(proc) = h.as_mut() {
+            if let Some(stdin) = proc.child.stdin.as_mut() {
+                if writeln!(stdin, "{}", command).is_ok() && stdin.flush().is_ok() {
+                    let mut reply = String::new();
+                    if proc.reader.read_line(&mut reply).is_ok() {
+                        return reply.starts_with("OK");
+                    }
                 }
             }
         }
@@ -108,6 +304,411 @@ fn run_as_root(helper: &RootHelper, command: &str) -> bool {
     false
 }
 
+/// `true` if `mac` is exactly 17 hex digits and colons, the length of an
+/// `xx:xx:xx:xx:xx:xx` address; where the colons sit is not checked. Used
+/// on both sides of the helper so the privileged side never builds an argv
+/// from an unvalidated MAC.
+fn is_valid_mac(mac: &str) -> bool {
+    Regex::new(r"^[0-9A-Fa-f:]{17}$").unwrap().is_match(mac)
+}
+
+/// Run an `iptables` invocation by argv (no shell), returning success.
+fn iptables(args: &[&str]) -> bool {
+    Command::new("iptables")
+        .args(args)
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+/// Execute one privileged verb and return its `OK`/`ERR` reply line. Runs on
+/// the root side only (the `--root-helper` loop and `--root-oneshot` path).
+fn handle_root_verb(line: &str) -> String {
+    let mut parts = line.split_whitespace();
+    let verb = parts.next().unwrap_or("");
+    match verb {
+        "BLOCK_MAC" => match parts.next() {
+            Some(mac) if is_valid_mac(mac) => {
+                let forward = iptables(&["-I", "FORWARD", "1", "-m", "mac", "--mac-source", mac, "-j", "DROP"]);
+                let input = iptables(&["-I", "INPUT", "1", "-m", "mac", "--mac-source", mac, "-j", "DROP"]);
+                if forward && input { "OK".to_string() } else { "ERR iptables failed".to_string() }
+            }
+            Some(_) => "ERR invalid MAC".to_string(),
+            None => "ERR missing MAC".to_string(),
+        },
+        "UNBLOCK_MAC" => match parts.next() {
+            Some(mac) if is_valid_mac(mac) => {
+                iptables(&["-D", "FORWARD", "-m", "mac", "--mac-source", mac, "-j", "DROP"]);
+                iptables(&["-D", "INPUT", "-m", "mac", "--mac-source", mac, "-j", "DROP"]);
+                "OK".to_string()
+            }
+            Some(_) => "ERR invalid MAC".to_string(),
+            None => "ERR missing MAC".to_string(),
+        },
+        "FLUSH" => {
+            iptables(&["-F", "FORWARD"]);
+            iptables(&["-F", "INPUT"]);
+            "OK".to_string()
+        }
+        "LIMIT_IP" => match (parts.next(), parts.next(), parts.next()) {
+            (Some(iface), Some(ip), Some(rate))
+                if is_valid_iface(iface)
+                    && is_valid_ip(ip)
+                    && !rate.is_empty()
+                    && rate.bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                limit_ip_tc(iface, ip, rate)
+            }
+            _ => "ERR invalid limit arguments".to_string(),
+        },
+        "UNLIMIT_IP" => match (parts.next(), parts.next()) {
+            (Some(iface), Some(ip)) if is_valid_iface(iface) && is_valid_ip(ip) => {
+                unlimit_ip_tc(iface, ip)
+            }
+            _ => "ERR invalid unlimit arguments".to_string(),
+        },
+        "" => "ERR empty command".to_string(),
+        other => format!("ERR unknown verb {}", other),
+    }
+}
+
+/// Run a `tc` invocation by argv, returning success.
+fn tc(args: &[&str]) -> bool {
+    Command::new("tc")
+        .args(args)
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+fn is_valid_ip(ip: &str) -> bool {
+    Regex::new(r"^\d{1,3}(\.\d{1,3}){3}$").unwrap().is_match(ip)
+}
+
+fn is_valid_iface(iface: &str) -> bool {
+    Regex::new(r"^[A-Za-z0-9_-]{1,15}$").unwrap().is_match(iface)
+}
+
+/// HTB class minor number for an IP — its last octet, which is unique within a
+/// single /24 hotspot subnet.
+fn ip_classid(ip: &str) -> u32 {
+    ip.rsplit('.')
+        .next()
+        .and_then(|o| o.parse::<u32>().ok())
+        .filter(|n| (1..=254).contains(n))
+        .unwrap_or(1)
+}
+
+/// Install a bidirectional cap for `ip` on `iface`. Download is shaped with an
+/// HTB class and a `u32` destination filter on egress; upload is policed on
+/// ingress (client→WAN) with a matching source filter, so a single "Limit…"
+/// caps both directions. `tc class/filter replace` makes re-application
+/// idempotent.
+fn limit_ip_tc(iface: &str, ip: &str, rate_kbit: &str) -> String {
+    let minor = ip_classid(ip);
+    let classid = format!("1:{}", minor);
+    // Per-IP filter handle in the u32 hash table, so each client's classifier
+    // can be deleted individually instead of wiping the whole `prio 1` band.
+    let fhandle = format!("800::{}", minor);
+    let rate = format!("{}kbit", rate_kbit);
+    let burst = format!("{}kbit", rate_kbit); // ~1s burst budget for the policer
+    let dst = format!("{}/32", ip);
+    let src = format!("{}/32", ip);
+    // Download: ensure a root HTB qdisc exists (ignored if already present).
+    tc(&["qdisc", "add", "dev", iface, "root", "handle", "1:", "htb"]);
+    let class_ok = tc(&["class", "replace", "dev", iface, "parent", "1:", "classid", &classid, "htb", "rate", &rate]);
+    let down_ok = tc(&["filter", "replace", "dev", iface, "protocol", "ip", "parent", "1:", "prio", "1", "handle", &fhandle, "u32", "match", "ip", "dst", &dst, "flowid", &classid]);
+    // Upload: police traffic arriving from the client on ingress.
+    tc(&["qdisc", "add", "dev", iface, "handle", "ffff:", "ingress"]);
+    let up_ok = tc(&["filter", "replace", "dev", iface, "parent", "ffff:", "protocol", "ip", "prio", "1", "handle", &fhandle, "u32", "match", "ip", "src", &src, "police", "rate", &rate, "burst", &burst, "drop", "flowid", ":1"]);
+    if class_ok && down_ok && up_ok {
+        "OK".to_string()
+    } else {
+        "ERR tc failed".to_string()
+    }
+}
+
+/// Remove the HTB class and both per-IP classifiers (egress download and
+/// ingress upload) capping `ip` on `iface`, leaving other clients' filters in
+/// place.
+fn unlimit_ip_tc(iface: &str, ip: &str) -> String {
+    let minor = ip_classid(ip);
+    let classid = format!("1:{}", minor);
+    let fhandle = format!("800::{}", minor);
+    tc(&["filter", "del", "dev", iface, "protocol", "ip", "parent", "1:", "prio", "1", "handle", &fhandle, "u32"]);
+    tc(&["filter", "del", "dev", iface, "parent", "ffff:", "protocol", "ip", "prio", "1", "handle", &fhandle, "u32"]);
+    tc(&["class", "del", "dev", iface, "classid", &classid]);
+    "OK".to_string()
+}
+
+/// Privileged loop: read one tuple per line from stdin, reply on stdout. Runs
+/// as root under pkexec; never evaluates a shell.
+fn run_root_helper_loop() {
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(_) => break,
+        };
+        let reply = handle_root_verb(line.trim());
+        if writeln!(stdout, "{}", reply).is_err() || stdout.flush().is_err() {
+            break;
+        }
+    }
+}
+
+/// Bind the control socket and serve clients on a background thread. A failure
+/// to bind (e.g. a stale socket owned by another instance) is non-fatal; the
+/// GTK window still works, the socket simply stays unavailable.
+fn spawn_control_listener(state: SharedControl) {
+    let path = control_socket_path();
+    let _ = std::fs::remove_file(&path);
+    let listener = match UnixListener::bind(&path) {
+        Ok(l) => l,
+        Err(_) => return,
+    };
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            if !APP_RUNNING.load(Ordering::SeqCst) {
+                break;
+            }
+            if let Ok(stream) = stream {
+                handle_control_client(stream, &state);
+            }
+        }
+    });
+}
+
+/// Read newline-terminated commands from a single client, replying with one
+/// `OK ...`/`ERR ...` line per command until the connection closes.
+fn handle_control_client(stream: UnixStream, state: &SharedControl) {
+    let mut writer = match stream.try_clone() {
+        Ok(w) => w,
+        Err(_) => return,
+    };
+    let reader = BufReader::new(stream);
+    for line in reader.lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(_) => break,
+        };
+        let reply = dispatch_control_command(line.trim(), state);
+        if writeln!(writer, "{}", reply).is_err() {
+            break;
+        }
+    }
+}
+
+/// Parse and execute a single control command, returning its one-line reply.
+/// The verbs mirror the GTK actions so the window and the socket stay in sync.
+fn dispatch_control_command(line: &str, state: &SharedControl) -> String {
+    let backend = state.backend.as_ref();
+    let mut parts = line.splitn(4, ' ');
+    let verb = parts.next().unwrap_or("").to_uppercase();
+    let interface = state.interface.lock().map(|i| i.clone()).unwrap_or_default();
+
+    match verb.as_str() {
+        "SETIFACE" => match parts.next() {
+            Some(name) if !name.is_empty() => {
+                if let Ok(mut iface) = state.interface.lock() {
+                    *iface = name.to_string();
+                }
+                format!("OK interface {}", name)
+            }
+            _ => "ERR missing interface".to_string(),
+        },
+        "START" => {
+            let ssid = parts.next().unwrap_or("");
+            let channel: u32 = parts
+                .next()
+                .and_then(|c| c.parse().ok())
+                .unwrap_or(state.channel);
+            let password = parts.next().unwrap_or("");
+            if interface.is_empty() {
+                return "ERR no wireless interface".to_string();
+            }
+            match start_hotspot(state.backend.clone(), state.hotspot.clone(), &interface, channel, ssid, password) {
+                Ok(msg) => {
+                    state.running.store(true, Ordering::SeqCst);
+                    format!("OK {}", msg)
+                }
+                Err(e) => format!("ERR:{} {}", e.exit_code(), e),
+            }
+        }
+        "STOP" => match stop_hotspot(backend, state.hotspot.as_ref(), &interface) {
+            Ok(()) => {
+                state.running.store(false, Ordering::SeqCst);
+                format!("OK Stopped on {}", interface)
+            }
+            Err(e) => format!("ERR:{} {}", e.exit_code(), e),
+        },
+        "LIST" => {
+            let devices = get_connected_devices(backend, &interface);
+            if devices.is_empty() {
+                return "OK".to_string();
+            }
+            let tuples: Vec<String> = devices
+                .iter()
+                .map(|d| {
+                    let ip = ip_for_mac_fs(&d.mac).unwrap_or_default();
+                    format!(
+                        "{} {} {} rx={} tx={}",
+                        d.mac,
+                        if d.hostname.is_empty() { "-" } else { &d.hostname },
+                        if ip.is_empty() { "-" } else { &ip },
+                        d.rx_bytes,
+                        d.tx_bytes
+                    )
+                })
+                .collect();
+            format!("OK {}", tuples.join("; "))
+        }
+        "BLOCK" => match parts.next() {
+            Some(mac) if block_device(backend, mac, &interface) => {
+                add_to_blocklist(mac);
+                format!("OK blocked {}", mac)
+            }
+            Some(mac) => format!("ERR could not block {}", mac),
+            None => "ERR BLOCK requires a MAC".to_string(),
+        },
+        "UNBLOCK" => match parts.next() {
+            Some(mac) => {
+                remove_from_blocklist(mac);
+                unblock_device(backend, mac);
+                format!("OK unblocked {}", mac)
+            }
+            None => "ERR UNBLOCK requires a MAC".to_string(),
+        },
+        "BLOCKLIST" => {
+            let blocked = load_blocklist();
+            if blocked.is_empty() {
+                "OK".to_string()
+            } else {
+                format!("OK {}", blocked.join(" "))
+            }
+        }
+        "SCAN" => {
+            let (iface, freq, err) = detect_interface(backend);
+            match err {
+                Some(e) => format!("ERR {}", e),
+                None => format!("OK {} Ch {} ({} MHz)", iface, freq_to_channel(freq), freq),
+            }
+        }
+        "" => "ERR empty command".to_string(),
+        other => format!("ERR unknown command {}", other),
+    }
+}
+
+/// When to emit ANSI colour on the control-reply output, resolved from the
+/// `--color=auto|always|never` flag. `Auto` colours only when stdout is a
+/// terminal, so piped or redirected output stays plain and parseable.
+#[derive(Clone, Copy)]
+enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Parse the `--color[=VALUE]` flag out of the raw argument vector,
+    /// defaulting to `Auto` and accepting both `--color=always` and
+    /// `--color always` spellings.
+    fn from_args(argv: &[String]) -> Self {
+        for (i, arg) in argv.iter().enumerate() {
+            let value = if let Some(v) = arg.strip_prefix("--color=") {
+                Some(v.to_string())
+            } else if arg == "--color" {
+                argv.get(i + 1).cloned()
+            } else {
+                None
+            };
+            if let Some(v) = value {
+                return match v.as_str() {
+                    "always" => ColorChoice::Always,
+                    "never" => ColorChoice::Never,
+                    _ => ColorChoice::Auto,
+                };
+            }
+        }
+        ColorChoice::Auto
+    }
+
+    /// Whether colour should actually be written to stdout right now.
+    fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => std::io::stdout().is_terminal(),
+        }
+    }
+}
+
+/// Wrap a control reply for display: green for `OK`, red for `ERR` when colour
+/// is enabled, otherwise the plain line unchanged.
+fn colorize_reply(reply: &str, color: ColorChoice) -> String {
+    if !color.enabled() {
+        return reply.to_string();
+    }
+    let trimmed = reply.trim_end_matches('\n');
+    let code = if trimmed.starts_with("OK") { "32" } else { "31" };
+    let suffix = &reply[trimmed.len()..];
+    format!("\x1b[{}m{}\x1b[0m{}", code, trimmed, suffix)
+}
+
+/// Connect to a running instance's control socket, send each newline-separated
+/// verb of `command`, print every reply and return the exit code that
+/// [`ctl_reply_code`] derives from the last one.
+fn run_ctl_client(command: &str, color: ColorChoice) -> u8 {
+    let path = control_socket_path();
+    let stream = match UnixStream::connect(&path) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("idkspot: cannot connect to {}: {}", path, e);
+            return 1;
+        }
+    };
+    let mut writer = match stream.try_clone() {
+        Ok(w) => w,
+        Err(e) => {
+            eprintln!("idkspot: {}", e);
+            return 1;
+        }
+    };
+    // A command may be several newline-separated verbs (e.g. `SETIFACE` before
+    // `START`); send each and read its reply, reporting on the last one.
+    let mut reader = BufReader::new(stream);
+    let mut code = 0;
+    for verb in command.lines() {
+        if writeln!(writer, "{}", verb).is_err() {
+            eprintln!("idkspot: failed to send command");
+            return 1;
+        }
+        let mut reply = String::new();
+        if reader.read_line(&mut reply).is_err() {
+            eprintln!("idkspot: no reply");
+            return 1;
+        }
+        print!("{}", colorize_reply(&reply, color));
+        code = ctl_reply_code(&reply);
+    }
+    code
+}
+
+/// Derive a process exit code from a control-socket reply: `OK ...` is success,
+/// `ERR:<n> ...` carries an explicit [`HotspotError::exit_code`], and a plain
+/// `ERR ...` maps to the generic failure code `1`.
+fn ctl_reply_code(reply: &str) -> u8 {
+    if reply.starts_with("OK") {
+        return 0;
+    }
+    reply
+        .strip_prefix("ERR:")
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|code| code.parse().ok())
+        .unwrap_or(1)
+}
+
 fn run_tray_service() {
     use ksni::{Tray, TrayService, menu::*};
 
@@ -139,9 +740,10 @@ fn run_tray_service() {
     let _ = handle;
 }
 
-fn build_ui(app: &Application, window_ref: Rc<RefCell<Option<ApplicationWindow>>>, root_helper: RootHelper) {
-    let (compatible, compat_message) = check_compatibility();
-    let (interface, frequency, detection_error) = detect_interface();
+fn build_ui(app: &Application, window_ref: Rc<RefCell<Option<ApplicationWindow>>>, control: SharedControl) {
+    let backend = control.backend.clone();
+    let (compatible, compat_message) = check_compatibility(backend.as_ref());
+    let (interface, frequency, detection_error) = detect_interface(backend.as_ref());
     let channel = freq_to_channel(frequency);
     let is_running = Rc::new(RefCell::new(false));
 
@@ -225,6 +827,44 @@ fn build_ui(app: &Application, window_ref: Rc<RefCell<Option<ApplicationWindow>>
     pass_box.append(&pass_entry);
     main_box.append(&pass_box);
 
+    // Auto channel selection: when ticked, scan the environment and pick the
+    // least-congested channel instead of whatever the card currently sits on.
+    let auto_check = gtk4::CheckButton::with_label("Auto channel");
+    auto_check.set_margin_top(4);
+    let recommended_channel = Rc::new(RefCell::new(channel));
+    let iface_for_scan = interface.clone();
+    let recommended_for_scan = recommended_channel.clone();
+    auto_check.connect_toggled(move |cb| {
+        if !cb.is_active() {
+            cb.set_label(Some("Auto channel"));
+            return;
+        }
+        // The scan runs `iw dev <iface> scan`, a multi-second operation, so do
+        // it off the main loop and update the label from the resolved future.
+        cb.set_label(Some("Auto channel (scanning…)"));
+        let iface = iface_for_scan.clone();
+        let recommended = recommended_for_scan.clone();
+        let cb_for_scan = cb.clone();
+        gtk4::glib::spawn_future_local(gtk4::glib::clone!(
+            #[weak] cb_for_scan,
+            async move {
+                match scan_channels_async(&iface).await {
+                    Some(scan) => {
+                        *recommended.borrow_mut() = scan.recommended_24;
+                        let mut label = format!("Auto (recommended: Ch {}", scan.recommended_24);
+                        if let Some(ch5) = scan.recommended_5 {
+                            label.push_str(&format!(" / Ch {}", ch5));
+                        }
+                        label.push(')');
+                        cb_for_scan.set_label(Some(&label));
+                    }
+                    None => cb_for_scan.set_label(Some("Auto channel")),
+                }
+            }
+        ));
+    });
+    main_box.append(&auto_check);
+
     let status_msg = Label::new(None);
     status_msg.set_margin_top(6);
     main_box.append(&status_msg);
@@ -234,6 +874,10 @@ fn build_ui(app: &Application, window_ref: Rc<RefCell<Option<ApplicationWindow>>
     action_button.add_css_class("pill");
     action_button.set_margin_top(8);
 
+    // Spinner shown while create_ap is coming up.
+    let spinner = gtk4::Spinner::new();
+    spinner.set_margin_top(8);
+
     let can_start = compatible && detection_error.is_none();
     action_button.set_sensitive(can_start);
 
@@ -253,8 +897,9 @@ fn build_ui(app: &Application, window_ref: Rc<RefCell<Option<ApplicationWindow>>
     let blocked_btn = Button::with_label("Blocked");
     blocked_btn.add_css_class("flat");
     let window_clone_for_blocked = window.clone();
+    let backend_for_blocked = backend.clone();
     blocked_btn.connect_clicked(move |_| {
-        show_blocked_dialog(&window_clone_for_blocked);
+        show_blocked_dialog(&window_clone_for_blocked, backend_for_blocked.clone());
     });
     devices_header.append(&blocked_btn);
     devices_frame.append(&devices_header);
@@ -282,23 +927,43 @@ fn build_ui(app: &Application, window_ref: Rc<RefCell<Option<ApplicationWindow>>
     let pass_entry_clone = pass_entry.clone();
     let button_clone = action_button.clone();
     let devices_frame_clone = devices_frame.clone();
+    let spinner_clone = spinner.clone();
+    let auto_check_clone = auto_check.clone();
+    let recommended_for_button = recommended_channel.clone();
+    let control_for_button = control.clone();
 
     action_button.connect_clicked(move |_| {
+        let backend = control_for_button.backend.as_ref();
         let mut running = is_running_clone.borrow_mut();
         if *running {
-            let result = stop_hotspot(&interface_clone);
-            status_msg_clone.set_text(&result);
+            match stop_hotspot(backend, control_for_button.hotspot.as_ref(), &interface_clone) {
+                Ok(()) => {
+                    status_msg_clone.remove_css_class("error");
+                    status_msg_clone.set_text(&format!("Stopped on {}", interface_clone));
+                }
+                Err(e) => {
+                    status_msg_clone.add_css_class("error");
+                    status_msg_clone.set_text(&e.to_string());
+                }
+            }
             button_clone.set_label("Start Hotspot");
             button_clone.remove_css_class("destructive-action");
             button_clone.add_css_class("suggested-action");
             ssid_entry_clone.set_sensitive(true);
             pass_entry_clone.set_sensitive(true);
             devices_frame_clone.set_visible(false);
+            spinner_clone.stop();
             *running = false;
+            control_for_button.running.store(false, Ordering::SeqCst);
         } else {
             let ssid = ssid_entry_clone.text().to_string();
             let password = pass_entry_clone.text().to_string();
-            match start_hotspot(&interface_clone, channel, &ssid, &password) {
+            let channel = if auto_check_clone.is_active() {
+                *recommended_for_button.borrow()
+            } else {
+                channel
+            };
+            match start_hotspot(control_for_button.backend.clone(), control_for_button.hotspot.clone(), &interface_clone, channel, &ssid, &password) {
                 Ok(msg) => {
                     status_msg_clone.set_text(&msg);
                     button_clone.set_label("Stop Hotspot");
@@ -307,10 +972,18 @@ fn build_ui(app: &Application, window_ref: Rc<RefCell<Option<ApplicationWindow>>
                     ssid_entry_clone.set_sensitive(false);
                     pass_entry_clone.set_sensitive(false);
                     devices_frame_clone.set_visible(true);
+                    // Spin until the AP has had time to come up and the first
+                    // station poll fires, then settle.
+                    spinner_clone.start();
+                    gtk4::glib::timeout_add_seconds_local_once(
+                        4,
+                        gtk4::glib::clone!(#[weak] spinner_clone, move || spinner_clone.stop()),
+                    );
                     *running = true;
+                    control_for_button.running.store(true, Ordering::SeqCst);
                 }
-                Err(msg) => {
-                    status_msg_clone.set_text(&msg);
+                Err(e) => {
+                    status_msg_clone.set_text(&e.to_string());
                     status_msg_clone.add_css_class("error");
                 }
             }
@@ -318,6 +991,7 @@ fn build_ui(app: &Application, window_ref: Rc<RefCell<Option<ApplicationWindow>>
     });
 
     main_box.append(&action_button);
+    main_box.append(&spinner);
 
     let tray_hint = Label::new(Some("Close window to minimize to tray"));
     tray_hint.add_css_class("dim-label");
@@ -339,28 +1013,96 @@ fn build_ui(app: &Application, window_ref: Rc<RefCell<Option<ApplicationWindow>>
     window.present();
     *window_ref.borrow_mut() = Some(window.clone());
 
+    // Keep the window in sync with the headless control socket: when a
+    // `--ctl START`/`STOP` toggles the shared `running` flag, mirror the change
+    // into the button, entries and device list here so the two control
+    // surfaces never drift apart.
+    let control_for_sync = control.clone();
+    let is_running_for_sync = is_running.clone();
+    let button_for_sync = action_button.clone();
+    let status_for_sync = status_msg.clone();
+    let ssid_for_sync = ssid_entry.clone();
+    let pass_for_sync = pass_entry.clone();
+    let devices_frame_for_sync = devices_frame.clone();
+    let spinner_for_sync = spinner.clone();
+    gtk4::glib::timeout_add_local(std::time::Duration::from_millis(500), move || {
+        let shared = control_for_sync.running.load(Ordering::SeqCst);
+        let mut local = is_running_for_sync.borrow_mut();
+        if shared != *local {
+            *local = shared;
+            if shared {
+                status_for_sync.remove_css_class("error");
+                status_for_sync.set_text("Hotspot running");
+                button_for_sync.set_label("Stop Hotspot");
+                button_for_sync.remove_css_class("suggested-action");
+                button_for_sync.add_css_class("destructive-action");
+                ssid_for_sync.set_sensitive(false);
+                pass_for_sync.set_sensitive(false);
+                devices_frame_for_sync.set_visible(true);
+            } else {
+                status_for_sync.set_text("Hotspot stopped");
+                button_for_sync.set_label("Start Hotspot");
+                button_for_sync.remove_css_class("destructive-action");
+                button_for_sync.add_css_class("suggested-action");
+                ssid_for_sync.set_sensitive(true);
+                pass_for_sync.set_sensitive(true);
+                devices_frame_for_sync.set_visible(false);
+                spinner_for_sync.stop();
+            }
+        }
+        gtk4::glib::ControlFlow::Continue
+    });
+
     // Refresh devices periodically
     let interface_for_refresh = interface.clone();
     let is_running_for_refresh = is_running.clone();
     let devices_list_clone = devices_list.clone();
     let no_devices_label_clone = no_devices_label.clone();
-    let root_helper_clone = root_helper.clone();
-    
+    let backend_for_refresh = backend.clone();
+    // Previous poll's cumulative byte counters per MAC, for throughput deltas.
+    let prev_bytes: Rc<RefCell<std::collections::HashMap<String, (u64, u64)>>> =
+        Rc::new(RefCell::new(std::collections::HashMap::new()));
+
     gtk4::glib::timeout_add_local(std::time::Duration::from_secs(2), move || {
         if *is_running_for_refresh.borrow() {
-            let devices = get_connected_devices(&interface_for_refresh);
-            while let Some(child) = devices_list_clone.first_child() {
-                devices_list_clone.remove(&child);
-            }
-            if devices.is_empty() {
-                no_devices_label_clone.set_visible(true);
-            } else {
-                no_devices_label_clone.set_visible(false);
-                for device in devices {
-                    let row = create_device_row(&device.0, &device.1, &interface_for_refresh, root_helper_clone.clone());
-                    devices_list_clone.append(&row);
+            let interface = interface_for_refresh.clone();
+            let backend = backend_for_refresh.clone();
+            let prev_bytes = prev_bytes.clone();
+            // Poll off the main loop: the iw/arp subprocesses resolve on glib's
+            // executor and we rebuild the list from the resolved future, holding
+            // only weak references so nothing outlives the widgets.
+            gtk4::glib::spawn_future_local(gtk4::glib::clone!(
+                #[weak] devices_list_clone,
+                #[weak] no_devices_label_clone,
+                async move {
+                    let devices = get_connected_devices_async(&interface).await;
+                    while let Some(child) = devices_list_clone.first_child() {
+                        devices_list_clone.remove(&child);
+                    }
+                    if devices.is_empty() {
+                        no_devices_label_clone.set_visible(true);
+                    } else {
+                        no_devices_label_clone.set_visible(false);
+                        let mut prev = prev_bytes.borrow_mut();
+                        for device in devices {
+                            // bytes/s over the 2-second poll interval. From the
+                            // AP's view `tx bytes` is what it sent to the client
+                            // (the client's download) and `rx bytes` is what it
+                            // received from the client (the client's upload).
+                            let (down, up) = match prev.get(&device.mac) {
+                                Some(&(prx, ptx)) => (
+                                    device.tx_bytes.saturating_sub(ptx) / 2,
+                                    device.rx_bytes.saturating_sub(prx) / 2,
+                                ),
+                                None => (0, 0),
+                            };
+                            prev.insert(device.mac.clone(), (device.rx_bytes, device.tx_bytes));
+                            let row = create_device_row(&device, down, up, &interface, backend.clone());
+                            devices_list_clone.append(&row);
+                        }
+                    }
                 }
-            }
+            ));
         }
         gtk4::glib::ControlFlow::Continue
     });
@@ -376,44 +1118,120 @@ fn build_ui(app: &Application, window_ref: Rc<RefCell<Option<ApplicationWindow>>
     });
 }
 
-fn create_device_row(mac: &str, hostname: &str, interface: &str, root_helper: RootHelper) -> ListBoxRow {
+fn create_device_row(device: &Device, down_bps: u64, up_bps: u64, interface: &str, backend: Arc<dyn SystemBackend>) -> ListBoxRow {
     let row = ListBoxRow::new();
     let hbox = GtkBox::new(Orientation::Horizontal, 12);
     hbox.set_margin_top(6);
     hbox.set_margin_bottom(6);
     hbox.set_margin_start(8);
     hbox.set_margin_end(8);
-    
+
     let info_box = GtkBox::new(Orientation::Vertical, 2);
     info_box.set_hexpand(true);
-    let name_label = Label::new(Some(if hostname.is_empty() { "Unknown Device" } else { hostname }));
+    let name_label = Label::new(Some(if device.hostname.is_empty() { "Unknown Device" } else { &device.hostname }));
     name_label.set_halign(gtk4::Align::Start);
     info_box.append(&name_label);
-    let mac_label = Label::new(Some(mac));
+    let mac_label = Label::new(Some(&device.mac));
     mac_label.set_halign(gtk4::Align::Start);
     mac_label.add_css_class("device-mac");
     info_box.append(&mac_label);
+    let rate_label = Label::new(Some(&format!("↓ {}  ↑ {}", format_rate(down_bps), format_rate(up_bps))));
+    rate_label.set_halign(gtk4::Align::Start);
+    rate_label.add_css_class("device-mac");
+    info_box.append(&rate_label);
     hbox.append(&info_box);
-    
+
+    // Limit… opens a small dialog and installs a tc HTB cap keyed by the
+    // client's leased IP.
+    let limit_btn = Button::with_label("Limit…");
+    limit_btn.add_css_class("flat");
+    let mac_for_limit = device.mac.clone();
+    let iface_for_limit = interface.to_string();
+    let backend_for_limit = backend.clone();
+    let row_for_limit = row.clone();
+    limit_btn.connect_clicked(move |_| {
+        show_limit_dialog(&row_for_limit, &mac_for_limit, &iface_for_limit, backend_for_limit.clone());
+    });
+    hbox.append(&limit_btn);
+
     let block_btn = Button::with_label("Block");
     block_btn.add_css_class("destructive-action");
-    
-    let mac_clone = mac.to_string();
+
+    let mac_clone = device.mac.clone();
     let iface_clone = interface.to_string();
     block_btn.connect_clicked(move |btn| {
-        if block_device(&mac_clone, &iface_clone, &root_helper) {
+        if block_device(backend.as_ref(), &mac_clone, &iface_clone) {
             add_to_blocklist(&mac_clone);
             btn.set_label("Blocked");
             btn.set_sensitive(false);
         }
     });
-    
+
     hbox.append(&block_btn);
     row.set_child(Some(&hbox));
     row
 }
 
-fn show_blocked_dialog(parent: &ApplicationWindow) {
+/// Format a byte-per-second rate for display (B/s, KB/s, MB/s).
+fn format_rate(bytes_per_sec: u64) -> String {
+    const KB: f64 = 1024.0;
+    const MB: f64 = 1024.0 * 1024.0;
+    let b = bytes_per_sec as f64;
+    if b >= MB {
+        format!("{:.1} MB/s", b / MB)
+    } else if b >= KB {
+        format!("{:.1} KB/s", b / KB)
+    } else {
+        format!("{} B/s", bytes_per_sec)
+    }
+}
+
+/// Ask for a per-client cap (kbit/s) and apply it via `tc`, persisting it so it
+/// survives restarts.
+fn show_limit_dialog(parent_row: &ListBoxRow, mac: &str, interface: &str, backend: Arc<dyn SystemBackend>) {
+    let window: Option<gtk4::Window> = parent_row.root().and_downcast::<gtk4::Window>();
+    let dialog = Dialog::builder()
+        .title("Rate Limit")
+        .modal(true)
+        .default_width(280)
+        .build();
+    if let Some(win) = window.as_ref() {
+        dialog.set_transient_for(Some(win));
+    }
+    dialog.add_button("Cancel", ResponseType::Cancel);
+    dialog.add_button("Apply", ResponseType::Apply);
+
+    let content = dialog.content_area();
+    content.set_margin_top(12);
+    content.set_margin_bottom(12);
+    content.set_margin_start(12);
+    content.set_margin_end(12);
+    content.append(&Label::new(Some("Cap per direction (kbit/s), 0 to clear:")));
+    let entry = Entry::new();
+    entry.set_text("1024");
+    content.append(&entry);
+
+    let mac = mac.to_string();
+    let interface = interface.to_string();
+    dialog.connect_response(move |dialog, response| {
+        if response == ResponseType::Apply {
+            if let Some(ip) = ip_for_mac_fs(&mac) {
+                let rate: u64 = entry.text().trim().parse().unwrap_or(0);
+                if rate == 0 {
+                    remove_limit(&ip);
+                    unlimit_ip(backend.as_ref(), &interface, &ip);
+                } else {
+                    limit_ip(backend.as_ref(), &interface, &ip, rate);
+                    add_limit(&ip, rate);
+                }
+            }
+        }
+        dialog.close();
+    });
+    dialog.present();
+}
+
+fn show_blocked_dialog(parent: &ApplicationWindow, backend: Arc<dyn SystemBackend>) {
     let dialog = Dialog::builder()
         .title("Blocked Devices")
         .transient_for(parent)
@@ -458,9 +1276,10 @@ fn show_blocked_dialog(parent: &ApplicationWindow) {
 
             let unblock_btn = Button::with_label("Unblock");
             let mac_clone = mac.clone();
+            let backend_clone = backend.clone();
             unblock_btn.connect_clicked(move |btn| {
                 remove_from_blocklist(&mac_clone);
-                unblock_device(&mac_clone);
+                unblock_device(backend_clone.as_ref(), &mac_clone);
                 btn.set_label("Unblocked");
                 btn.set_sensitive(false);
             });
@@ -477,27 +1296,109 @@ fn show_blocked_dialog(parent: &ApplicationWindow) {
     dialog.present();
 }
 
-fn block_device(mac: &str, interface: &str, root_helper: &RootHelper) -> bool {
-    // Try root helper first
-    let cmd = format!("iptables -I FORWARD 1 -m mac --mac-source {} -j DROP; iptables -I INPUT 1 -m mac --mac-source {} -j DROP", mac, mac);
-    if run_as_root(root_helper, &cmd) {
-        // Give it a moment to execute
-        std::thread::sleep(std::time::Duration::from_millis(100));
+fn block_device(backend: &dyn SystemBackend, mac: &str, _interface: &str) -> bool {
+    if !is_valid_mac(mac) {
+        return false;
+    }
+    // Persistent helper first; it replies OK/ERR so we know the result.
+    if backend.run_root(&format!("BLOCK_MAC {}", mac)) {
         return true;
     }
-    
-    // Fallback: direct pkexec (will ask for password)
-    let result = Command::new("pkexec")
-        .args(["sh", "-c", &cmd])
-        .status();
-    result.map(|s| s.success()).unwrap_or(false)
+    // Fallback: a single privileged invocation of ourselves (asks for a
+    // password) — still argv-only, no shell interpolation.
+    root_oneshot(backend, &["BLOCK_MAC", mac])
 }
 
-fn unblock_device(mac: &str) {
-    // Try to remove iptables rules (may fail if root helper is gone, but that's ok)
-    let _ = Command::new("pkexec")
-        .args(["sh", "-c", &format!("iptables -D FORWARD -m mac --mac-source {} -j DROP 2>/dev/null; iptables -D INPUT -m mac --mac-source {} -j DROP 2>/dev/null", mac, mac)])
-        .status();
+fn unblock_device(backend: &dyn SystemBackend, mac: &str) {
+    if !is_valid_mac(mac) {
+        return;
+    }
+    if !backend.run_root(&format!("UNBLOCK_MAC {}", mac)) {
+        let _ = root_oneshot(backend, &["UNBLOCK_MAC", mac]);
+    }
+}
+
+/// Fallback privileged action: re-exec ourselves under pkexec in
+/// `--root-oneshot` mode so the verb is handled by [`handle_root_verb`] with
+/// no shell involved.
+fn root_oneshot(backend: &dyn SystemBackend, verb_args: &[&str]) -> bool {
+    let exe = match std::env::current_exe() {
+        Ok(p) => p.to_string_lossy().into_owned(),
+        Err(_) => return false,
+    };
+    let mut argv = vec!["pkexec", exe.as_str(), "--root-oneshot"];
+    argv.extend_from_slice(verb_args);
+    backend
+        .run(&argv)
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+/// Send a download and upload cap for `ip` to the privileged side (persistent helper,
+/// falling back to a one-shot invocation).
+fn limit_ip(backend: &dyn SystemBackend, interface: &str, ip: &str, rate_kbit: u64) -> bool {
+    if !is_valid_ip(ip) || !is_valid_iface(interface) {
+        return false;
+    }
+    let rate = rate_kbit.to_string();
+    if backend.run_root(&format!("LIMIT_IP {} {} {}", interface, ip, rate)) {
+        return true;
+    }
+    root_oneshot(backend, &["LIMIT_IP", interface, ip, &rate])
+}
+
+/// Remove the cap for `ip` via the privileged side.
+fn unlimit_ip(backend: &dyn SystemBackend, interface: &str, ip: &str) {
+    if !is_valid_ip(ip) || !is_valid_iface(interface) {
+        return;
+    }
+    if !backend.run_root(&format!("UNLIMIT_IP {} {}", interface, ip)) {
+        let _ = root_oneshot(backend, &["UNLIMIT_IP", interface, ip]);
+    }
+}
+
+/// Re-install every persisted limit, e.g. after the hotspot restarts.
+fn apply_saved_limits(backend: &dyn SystemBackend, interface: &str) {
+    for (ip, rate) in load_limits() {
+        limit_ip(backend, interface, &ip, rate);
+    }
+}
+
+const LIMITS_FILE: &str = "/tmp/idkspot_limits.txt";
+
+fn load_limits() -> Vec<(String, u64)> {
+    std::fs::read_to_string(LIMITS_FILE)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let ip = parts.next()?;
+            let rate = parts.next()?.parse().ok()?;
+            Some((ip.to_string(), rate))
+        })
+        .collect()
+}
+
+fn save_limits(limits: &[(String, u64)]) {
+    let body = limits
+        .iter()
+        .map(|(ip, rate)| format!("{} {}", ip, rate))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let _ = std::fs::write(LIMITS_FILE, body);
+}
+
+fn add_limit(ip: &str, rate_kbit: u64) {
+    let mut limits = load_limits();
+    limits.retain(|(i, _)| i != ip);
+    limits.push((ip.to_string(), rate_kbit));
+    save_limits(&limits);
+}
+
+fn remove_limit(ip: &str) {
+    let mut limits = load_limits();
+    limits.retain(|(i, _)| i != ip);
+    save_limits(&limits);
 }
 
 fn add_to_blocklist(mac: &str) {
@@ -523,34 +1424,63 @@ fn load_blocklist() -> Vec<String> {
         .collect()
 }
 
-fn get_connected_devices(interface: &str) -> Vec<(String, String)> {
+/// A connected client, with the cumulative rx/tx byte counters reported by the
+/// station dump (zero for devices only seen via `arp`).
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Device {
+    mac: String,
+    hostname: String,
+    rx_bytes: u64,
+    tx_bytes: u64,
+}
+
+/// Parse `iw ... station dump` into `(mac, rx_bytes, tx_bytes)` per station.
+/// The `rx/tx bytes` lines belong to the most recently seen `Station` header.
+fn parse_station_dump(output: &str) -> Vec<(String, u64, u64)> {
+    let station_re = Regex::new(r"Station ([0-9a-fA-F:]{17})").unwrap();
+    let rx_re = Regex::new(r"rx bytes:\s*(\d+)").unwrap();
+    let tx_re = Regex::new(r"tx bytes:\s*(\d+)").unwrap();
+    let mut stations: Vec<(String, u64, u64)> = Vec::new();
+    for line in output.lines() {
+        if let Some(cap) = station_re.captures(line) {
+            stations.push((cap[1].to_uppercase(), 0, 0));
+        } else if let Some(cap) = rx_re.captures(line) {
+            if let Some(last) = stations.last_mut() {
+                last.1 = cap[1].parse().unwrap_or(0);
+            }
+        } else if let Some(cap) = tx_re.captures(line) {
+            if let Some(last) = stations.last_mut() {
+                last.2 = cap[1].parse().unwrap_or(0);
+            }
+        }
+    }
+    stations
+}
+
+fn get_connected_devices(backend: &dyn SystemBackend, interface: &str) -> Vec<Device> {
     let mut devices = Vec::new();
     let blocked = load_blocklist();
-    
-    if let Ok(output) = Command::new("iw").args(["dev", interface, "station", "dump"]).output() {
+
+    if let Ok(output) = backend.run(&["iw", "dev", interface, "station", "dump"]) {
         let stdout = String::from_utf8_lossy(&output.stdout);
-        let mac_re = Regex::new(r"Station ([0-9a-fA-F:]{17})").unwrap();
-        for cap in mac_re.captures_iter(&stdout) {
-            if let Some(mac) = cap.get(1) {
-                let mac_str = mac.as_str().to_uppercase();
-                if !blocked.contains(&mac_str) {
-                    let hostname = get_hostname_for_mac(&mac_str);
-                    devices.push((mac_str, hostname));
-                }
+        for (mac, rx, tx) in parse_station_dump(&stdout) {
+            if !blocked.contains(&mac) {
+                let hostname = get_hostname_for_mac(backend, &mac);
+                devices.push(Device { mac, hostname, rx_bytes: rx, tx_bytes: tx });
             }
         }
     }
-    
+
     if devices.is_empty() {
-        if let Ok(output) = Command::new("arp").arg("-n").output() {
+        if let Ok(output) = backend.run(&["arp", "-n"]) {
             let stdout = String::from_utf8_lossy(&output.stdout);
             let arp_re = Regex::new(r"(\d+\.\d+\.\d+\.\d+)\s+\S+\s+([0-9a-fA-F:]{17})").unwrap();
             for cap in arp_re.captures_iter(&stdout) {
                 if let Some(mac) = cap.get(2) {
                     let mac_str = mac.as_str().to_uppercase();
-                    if !blocked.contains(&mac_str) && !devices.iter().any(|(m, _)| m == &mac_str) {
-                        let hostname = get_hostname_for_mac(&mac_str);
-                        devices.push((mac_str, hostname));
+                    if !blocked.contains(&mac_str) && !devices.iter().any(|d| d.mac == mac_str) {
+                        let hostname = get_hostname_for_mac(backend, &mac_str);
+                        devices.push(Device { mac: mac_str, hostname, ..Default::default() });
                     }
                 }
             }
@@ -559,22 +1489,103 @@ fn get_connected_devices(interface: &str) -> Vec<(String, String)> {
     devices
 }
 
-fn get_hostname_for_mac(mac: &str) -> String {
-    for path in ["/var/lib/misc/dnsmasq.leases", "/tmp/dnsmasq.leases"] {
+const LEASE_FILES: [&str; 2] = ["/var/lib/misc/dnsmasq.leases", "/tmp/dnsmasq.leases"];
+
+fn get_hostname_for_mac(backend: &dyn SystemBackend, mac: &str) -> String {
+    for path in LEASE_FILES {
+        if let Ok(content) = backend.read_file(path) {
+            if let Some(host) = parse_lease_hostname(&content, mac) {
+                return host;
+            }
+        }
+    }
+    String::new()
+}
+
+/// Find the hostname a dnsmasq lease file records for `mac`, if any.
+fn parse_lease_hostname(content: &str, mac: &str) -> Option<String> {
+    for line in content.lines() {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() >= 4 && parts[1].eq_ignore_ascii_case(mac) {
+            return Some(parts[3].to_string());
+        }
+    }
+    None
+}
+
+/// Hostname lookup for the async path, reading lease files directly.
+fn hostname_for_mac_fs(mac: &str) -> String {
+    for path in LEASE_FILES {
+        if let Ok(content) = std::fs::read_to_string(path) {
+            if let Some(host) = parse_lease_hostname(&content, mac) {
+                return host;
+            }
+        }
+    }
+    String::new()
+}
+
+/// IP address a dnsmasq lease file records for `mac`, if any. Used to key the
+/// `tc` rate-limiting rules.
+fn ip_for_mac_fs(mac: &str) -> Option<String> {
+    for path in LEASE_FILES {
         if let Ok(content) = std::fs::read_to_string(path) {
             for line in content.lines() {
                 let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 4 && parts[1].eq_ignore_ascii_case(mac) {
-                    return parts[3].to_string();
+                if parts.len() >= 3 && parts[1].eq_ignore_ascii_case(mac) {
+                    return Some(parts[2].to_string());
                 }
             }
         }
     }
-    String::new()
+    None
 }
 
-fn check_compatibility() -> (bool, String) {
-    let output = match Command::new("iw").arg("list").output() { Ok(o) => o, Err(e) => return (false, format!("iw list failed: {}", e)) };
+/// Run a command through glib's async executor and yield its stdout, so the
+/// GTK main loop keeps servicing events while `iw`/`arp` run. Returns `None`
+/// if the process could not be spawned or produced no UTF-8 output.
+async fn subprocess_stdout(argv: &[&str]) -> Option<String> {
+    let args: Vec<&std::ffi::OsStr> = argv.iter().map(std::ffi::OsStr::new).collect();
+    let proc = gio::Subprocess::newv(&args, gio::SubprocessFlags::STDOUT_PIPE).ok()?;
+    let (stdout, _stderr) = proc.communicate_utf8_future(None).await.ok()?;
+    stdout.map(|s| s.to_string())
+}
+
+/// Async counterpart to [`get_connected_devices`] used by the GTK refresh
+/// loop: the blocking `iw`/`arp` calls become `gio::Subprocess` futures so the
+/// 2-second poll never stalls the main thread.
+async fn get_connected_devices_async(interface: &str) -> Vec<Device> {
+    let mut devices = Vec::new();
+    let blocked = load_blocklist();
+
+    if let Some(stdout) = subprocess_stdout(&["iw", "dev", interface, "station", "dump"]).await {
+        for (mac, rx, tx) in parse_station_dump(&stdout) {
+            if !blocked.contains(&mac) {
+                let hostname = hostname_for_mac_fs(&mac);
+                devices.push(Device { mac, hostname, rx_bytes: rx, tx_bytes: tx });
+            }
+        }
+    }
+
+    if devices.is_empty() {
+        if let Some(stdout) = subprocess_stdout(&["arp", "-n"]).await {
+            let arp_re = Regex::new(r"(\d+\.\d+\.\d+\.\d+)\s+\S+\s+([0-9a-fA-F:]{17})").unwrap();
+            for cap in arp_re.captures_iter(&stdout) {
+                if let Some(mac) = cap.get(2) {
+                    let mac_str = mac.as_str().to_uppercase();
+                    if !blocked.contains(&mac_str) && !devices.iter().any(|d| d.mac == mac_str) {
+                        let hostname = hostname_for_mac_fs(&mac_str);
+                        devices.push(Device { mac: mac_str, hostname, ..Default::default() });
+                    }
+                }
+            }
+        }
+    }
+    devices
+}
+
+fn check_compatibility(backend: &dyn SystemBackend) -> (bool, String) {
+    let output = match backend.run(&["iw", "list"]) { Ok(o) => o, Err(e) => return (false, format!("iw list failed: {}", e)) };
     let stdout = String::from_utf8_lossy(&output.stdout);
     let mut in_valid = false;
     let managed_re = Regex::new(r"(?i)#\{[^}]*\bmanaged\b[^}]*\}").unwrap();
@@ -589,8 +1600,8 @@ fn check_compatibility() -> (bool, String) {
     (false, "AP+Managed not found".to_string())
 }
 
-fn detect_interface() -> (String, u32, Option<String>) {
-    let output = match Command::new("iw").arg("dev").output() { Ok(o) => o, Err(e) => return (String::new(), 0, Some(format!("iw dev failed: {}", e))) };
+fn detect_interface(backend: &dyn SystemBackend) -> (String, u32, Option<String>) {
+    let output = match backend.run(&["iw", "dev"]) { Ok(o) => o, Err(e) => return (String::new(), 0, Some(format!("iw dev failed: {}", e))) };
     let stdout = String::from_utf8_lossy(&output.stdout);
     let iface_re = Regex::new(r"Interface\s+(\w+)").unwrap();
     let freq_re = Regex::new(r"channel\s+\d+\s+\((\d+)\s+MHz\)").unwrap();
@@ -613,22 +1624,715 @@ fn freq_to_channel(freq: u32) -> u32 {
     }
 }
 
-fn start_hotspot(interface: &str, channel: u32, ssid: &str, password: &str) -> Result<String, String> {
-    if ssid.is_empty() { return Err("SSID required".to_string()); }
-    if password.len() < 8 { return Err("Password needs 8+ chars".to_string()); }
+/// A client associated with a running hotspot.
+struct HotspotClient {
+    mac: String,
+    ip: String,
+    hostname: String,
+}
+
+/// A running hotspot and the clients connected to it. The fields are kept
+/// backend-agnostic so the `status` subcommand can render the same record as a
+/// table or as JSON.
+struct HotspotStatus {
+    interface: String,
+    ssid: String,
+    channel: u32,
+    clients: Vec<HotspotClient>,
+}
+
+/// Read the SSID and channel a specific interface is serving from
+/// `iw dev <iface> info`.
+fn ssid_and_channel(backend: &dyn SystemBackend, interface: &str) -> (String, u32) {
+    let output = match backend.run(&["iw", "dev", interface, "info"]) {
+        Ok(o) => o,
+        Err(_) => return (String::new(), 0),
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let ssid_re = Regex::new(r"(?m)^\s*ssid\s+(.+)$").unwrap();
+    let chan_re = Regex::new(r"channel\s+(\d+)").unwrap();
+    let ssid = ssid_re
+        .captures(&stdout)
+        .map(|c| c[1].trim().to_string())
+        .unwrap_or_default();
+    let channel = chan_re
+        .captures(&stdout)
+        .and_then(|c| c[1].parse().ok())
+        .unwrap_or(0);
+    (ssid, channel)
+}
+
+/// Collect the clients on `interface` from the station dump and the DHCP
+/// leases, pairing each MAC with its leased IP and hostname.
+fn status_clients(backend: &dyn SystemBackend, interface: &str) -> Vec<HotspotClient> {
+    get_connected_devices(backend, interface)
+        .into_iter()
+        .map(|d| HotspotClient {
+            ip: ip_for_mac_fs(&d.mac).unwrap_or_default(),
+            hostname: d.hostname,
+            mac: d.mac,
+        })
+        .collect()
+}
+
+/// Parse `create_ap --list-running` into `(ap_interface, ssid)` pairs, one per
+/// running instance. Lines look like `<pid> <wifi-iface> <ap-iface> <ssid>`.
+fn parse_create_ap_list(output: &str) -> Vec<(String, String)> {
+    let mut instances = Vec::new();
+    for line in output.lines() {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() >= 4 && parts[0].chars().all(|c| c.is_ascii_digit()) {
+            instances.push((parts[2].to_string(), parts[3..].join(" ")));
+        }
+    }
+    instances
+}
+
+/// Enumerate the hotspots this machine is currently serving. Prefers
+/// `create_ap`'s own running-instance listing when available, otherwise falls
+/// back to the detected wireless interface and its station dump.
+fn collect_status(backend: &dyn SystemBackend) -> Vec<HotspotStatus> {
+    if let Ok(out) = backend.run(&["create_ap", "--list-running"]) {
+        let instances = parse_create_ap_list(&String::from_utf8_lossy(&out.stdout));
+        if !instances.is_empty() {
+            return instances
+                .into_iter()
+                .map(|(iface, ssid)| {
+                    let (_, channel) = ssid_and_channel(backend, &iface);
+                    HotspotStatus {
+                        clients: status_clients(backend, &iface),
+                        channel,
+                        interface: iface,
+                        ssid,
+                    }
+                })
+                .collect();
+        }
+    }
+
+    let (iface, freq, err) = detect_interface(backend);
+    if err.is_some() || iface.is_empty() {
+        return Vec::new();
+    }
+    let (ssid, mut channel) = ssid_and_channel(backend, &iface);
+    if channel == 0 {
+        channel = freq_to_channel(freq);
+    }
+    vec![HotspotStatus {
+        clients: status_clients(backend, &iface),
+        interface: iface,
+        ssid,
+        channel,
+    }]
+}
+
+/// Escape a string for inclusion in a JSON document.
+fn json_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Render the collected hotspots as JSON, for scripts that want structured
+/// output.
+fn render_status_json(hotspots: &[HotspotStatus]) -> String {
+    let mut out = String::from("[");
+    for (i, h) in hotspots.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str(&format!(
+            "{{\"interface\":\"{}\",\"ssid\":\"{}\",\"channel\":{},\"clients\":[",
+            json_escape(&h.interface),
+            json_escape(&h.ssid),
+            h.channel
+        ));
+        for (j, c) in h.clients.iter().enumerate() {
+            if j > 0 {
+                out.push(',');
+            }
+            out.push_str(&format!(
+                "{{\"mac\":\"{}\",\"ip\":\"{}\",\"hostname\":\"{}\"}}",
+                json_escape(&c.mac),
+                json_escape(&c.ip),
+                json_escape(&c.hostname)
+            ));
+        }
+        out.push_str("]}");
+    }
+    out.push(']');
+    out
+}
+
+/// Render the collected hotspots as a human-readable table.
+fn render_status_table(hotspots: &[HotspotStatus]) -> String {
+    if hotspots.is_empty() {
+        return "No active hotspots.".to_string();
+    }
+    let mut out = String::new();
+    for h in hotspots {
+        out.push_str(&format!(
+            "{} \"{}\" (ch {})\n",
+            h.interface, h.ssid, h.channel
+        ));
+        if h.clients.is_empty() {
+            out.push_str("  no clients\n");
+        } else {
+            for c in &h.clients {
+                let host = if c.hostname.is_empty() { "-" } else { &c.hostname };
+                let ip = if c.ip.is_empty() { "-" } else { &c.ip };
+                out.push_str(&format!("  {}  {}  {}\n", c.mac, ip, host));
+            }
+        }
+    }
+    out
+}
+
+/// Print the current hotspot status once in either table or JSON form.
+fn print_status(backend: &dyn SystemBackend, json: bool) {
+    let hotspots = collect_status(backend);
+    if json {
+        println!("{}", render_status_json(&hotspots));
+    } else {
+        print!("{}", render_status_table(&hotspots));
+    }
+}
+
+/// Recommended channels from an environment scan.
+struct ChannelScan {
+    recommended_24: u32,
+    recommended_5: Option<u32>,
+}
+
+/// Candidate 2.4 GHz channels: the three non-overlapping choices.
+const CANDIDATES_24: [u32; 3] = [1, 6, 11];
+/// Candidate non-DFS 5 GHz channels (UNII-1 and UNII-3).
+const CANDIDATES_5: [u32; 9] = [36, 40, 44, 48, 149, 153, 157, 161, 165];
+
+/// Parse `iw dev <iface> scan` output into `(freq_mhz, signal_dbm)` pairs, one
+/// per neighbouring BSS that reported both a frequency and a signal.
+fn parse_scan(output: &str) -> Vec<(u32, f64)> {
+    let freq_re = Regex::new(r"freq:\s*(\d+)").unwrap();
+    let signal_re = Regex::new(r"signal:\s*(-?\d+(?:\.\d+)?)\s*dBm").unwrap();
+    let mut neighbors = Vec::new();
+    let mut current_freq: Option<u32> = None;
+    for line in output.lines() {
+        if line.trim_start().starts_with("BSS ") {
+            current_freq = None;
+        }
+        if let Some(caps) = freq_re.captures(line) {
+            current_freq = caps.get(1).and_then(|m| m.as_str().parse().ok());
+        }
+        if let Some(caps) = signal_re.captures(line) {
+            if let (Some(freq), Some(sig)) = (current_freq, caps.get(1).and_then(|m| m.as_str().parse::<f64>().ok())) {
+                neighbors.push((freq, sig));
+            }
+        }
+    }
+    neighbors
+}
+
+/// Linear-power congestion score for a candidate channel: sum the linear power
+/// (`10^(signal/10)`) of every neighbour that overlaps it. 2.4 GHz channels
+/// overlap within ±4 of the candidate; 5 GHz channels only collide on the same
+/// channel number.
+fn channel_score(candidate: u32, neighbors: &[(u32, f64)]) -> f64 {
+    let is_24 = candidate <= 14;
+    neighbors
+        .iter()
+        .filter_map(|&(freq, signal)| {
+            let ch = freq_to_channel(freq);
+            let overlaps = if is_24 {
+                freq >= 2400 && freq < 2500 && (ch as i32 - candidate as i32).abs() <= 4
+            } else {
+                freq >= 5000 && ch == candidate
+            };
+            overlaps.then(|| 10f64.powf(signal / 10.0))
+        })
+        .sum()
+}
+
+/// Pick the least-congested 2.4 GHz channel among {1,6,11} and the clearest
+/// non-DFS 5 GHz channel from a parsed neighbour list.
+fn recommend_channels(neighbors: &[(u32, f64)]) -> ChannelScan {
+    let recommended_24 = *CANDIDATES_24
+        .iter()
+        .min_by(|a, b| channel_score(**a, neighbors).total_cmp(&channel_score(**b, neighbors)))
+        .unwrap_or(&6);
+    let has_5ghz = neighbors.iter().any(|&(freq, _)| freq >= 5000);
+    let recommended_5 = if has_5ghz {
+        CANDIDATES_5
+            .iter()
+            .min_by(|a, b| channel_score(**a, neighbors).total_cmp(&channel_score(**b, neighbors)))
+            .copied()
+    } else {
+        None
+    };
+    ChannelScan { recommended_24, recommended_5 }
+}
+
+/// Scan the RF environment on `interface` and recommend the least-congested
+/// channels. The interface is brought up first (scanning requires it), then
+/// `iw dev <iface> scan` output is parsed and scored. Runs on glib's executor
+/// via [`subprocess_stdout`] so the multi-second scan never stalls the GTK
+/// main loop.
+async fn scan_channels_async(interface: &str) -> Option<ChannelScan> {
+    let _ = subprocess_stdout(&["ip", "link", "set", interface, "up"]).await;
+    let text = subprocess_stdout(&["iw", "dev", interface, "scan"]).await?;
+    Some(recommend_channels(&parse_scan(&text)))
+}
+
+/// Why bringing the hotspot up or down failed. Each variant maps to a distinct
+/// process exit code (see [`HotspotError::exit_code`]) so shell scripts can
+/// tell what went wrong.
+#[derive(Debug)]
+enum HotspotError {
+    /// The SSID/password did not pass validation.
+    InvalidConfig(String),
+    /// No usable hotspot backend (or `pkexec`) was found.
+    BackendMissing,
+    /// The backend process could not be spawned.
+    SpawnFailed(String),
+    /// The backend spawned but exited with a non-zero status.
+    NonZeroExit(i32),
+    /// The privileged action was denied.
+    PermissionDenied,
+}
+
+impl std::fmt::Display for HotspotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HotspotError::InvalidConfig(msg) => write!(f, "{}", msg),
+            HotspotError::BackendMissing => write!(f, "no usable hotspot backend found"),
+            HotspotError::SpawnFailed(e) => write!(f, "failed to start backend: {}", e),
+            HotspotError::NonZeroExit(code) => write!(f, "backend exited with status {}", code),
+            HotspotError::PermissionDenied => write!(f, "permission denied"),
+        }
+    }
+}
+
+impl std::error::Error for HotspotError {}
+
+impl HotspotError {
+    /// Conventional exit code for this error, kept stable for scripting.
+    fn exit_code(&self) -> u8 {
+        match self {
+            HotspotError::InvalidConfig(_) => 2,
+            HotspotError::BackendMissing => 3,
+            HotspotError::SpawnFailed(_) => 4,
+            HotspotError::NonZeroExit(_) => 5,
+            HotspotError::PermissionDenied => 6,
+        }
+    }
+}
+
+/// Validate the SSID/password before handing them to a backend.
+fn validate_config(ssid: &str, password: &str) -> Result<(), HotspotError> {
+    if ssid.is_empty() {
+        return Err(HotspotError::InvalidConfig("SSID required".to_string()));
+    }
+    if password.len() < 8 {
+        return Err(HotspotError::InvalidConfig("Password needs 8+ chars".to_string()));
+    }
+    Ok(())
+}
+
+/// A way of bringing an access point up and down. Different distros ship
+/// different tooling (`create_ap`, NetworkManager's `nmcli`, raw `hostapd`), so
+/// the concrete backend is chosen at runtime by [`detect_hotspot_backend`].
+/// Commands are issued through the [`SystemBackend`] so the choice stays
+/// testable.
+trait HotspotBackend: Send + Sync {
+    /// Name of the underlying tool, for display and the `--backend` override.
+    fn name(&self) -> &'static str;
+    fn start(&self, sys: &dyn SystemBackend, interface: &str, channel: u32, ssid: &str, password: &str) -> Result<(), HotspotError>;
+    fn stop(&self, sys: &dyn SystemBackend, interface: &str) -> Result<(), HotspotError>;
+}
+
+/// Run a backend command and translate its outcome into a [`HotspotError`],
+/// capturing the child's own exit status and stderr rather than only detecting
+/// a failed spawn.
+fn spawn_result(sys: &dyn SystemBackend, argv: &[&str]) -> Result<(), HotspotError> {
+    match sys.run(argv) {
+        Ok(out) => {
+            if out.status.success() {
+                Ok(())
+            } else {
+                let stderr = String::from_utf8_lossy(&out.stderr);
+                if !stderr.trim().is_empty() {
+                    eprintln!("idkspot: {}: {}", argv.join(" "), stderr.trim());
+                }
+                Err(HotspotError::NonZeroExit(out.status.code().unwrap_or(-1)))
+            }
+        }
+        Err(e) => match e.kind() {
+            std::io::ErrorKind::NotFound => Err(HotspotError::BackendMissing),
+            std::io::ErrorKind::PermissionDenied => Err(HotspotError::PermissionDenied),
+            _ => Err(HotspotError::SpawnFailed(e.to_string())),
+        },
+    }
+}
+
+struct CreateApBackend;
+impl HotspotBackend for CreateApBackend {
+    fn name(&self) -> &'static str { "create_ap" }
+    fn start(&self, sys: &dyn SystemBackend, interface: &str, channel: u32, ssid: &str, password: &str) -> Result<(), HotspotError> {
+        let channel = channel.to_string();
+        spawn_result(sys, &["pkexec", "create_ap", "-c", &channel, interface, interface, ssid, password])
+    }
+    fn stop(&self, sys: &dyn SystemBackend, interface: &str) -> Result<(), HotspotError> {
+        spawn_result(sys, &["pkexec", "create_ap", "--stop", interface])
+    }
+}
+
+struct NmcliBackend;
+impl HotspotBackend for NmcliBackend {
+    fn name(&self) -> &'static str { "nmcli" }
+    fn start(&self, sys: &dyn SystemBackend, interface: &str, _channel: u32, ssid: &str, password: &str) -> Result<(), HotspotError> {
+        spawn_result(sys, &["pkexec", "nmcli", "device", "wifi", "hotspot", "ifname", interface, "ssid", ssid, "password", password])
+    }
+    fn stop(&self, sys: &dyn SystemBackend, interface: &str) -> Result<(), HotspotError> {
+        spawn_result(sys, &["pkexec", "nmcli", "device", "disconnect", interface])
+    }
+}
+
+const HOSTAPD_CONF: &str = "/tmp/idkspot-hostapd.conf";
+const HOSTAPD_PID: &str = "/tmp/idkspot-hostapd.pid";
+const DNSMASQ_PID: &str = "/tmp/idkspot-dnsmasq.pid";
+/// Gateway address idkspot assigns to the AP interface; clients get the rest of
+/// this /24 from dnsmasq.
+const HOSTAPD_GATEWAY: &str = "192.168.12.1";
+const HOSTAPD_CIDR: &str = "192.168.12.1/24";
+const HOSTAPD_SUBNET: &str = "192.168.12.0/24";
+const HOSTAPD_DHCP_RANGE: &str = "192.168.12.10,192.168.12.200,12h";
+
+struct HostapdBackend;
+impl HotspotBackend for HostapdBackend {
+    fn name(&self) -> &'static str { "hostapd" }
+    fn start(&self, sys: &dyn SystemBackend, interface: &str, channel: u32, ssid: &str, password: &str) -> Result<(), HotspotError> {
+        let conf = format!(
+            "interface={}\nssid={}\nchannel={}\nhw_mode=g\nauth_algs=1\nwpa=2\nwpa_key_mgmt=WPA-PSK\nrsn_pairwise=CCMP\nwpa_passphrase={}\n",
+            interface, ssid, channel, password
+        );
+        std::fs::write(HOSTAPD_CONF, conf).map_err(|e| HotspotError::SpawnFailed(e.to_string()))?;
+
+        // Give the AP interface an address so dnsmasq can serve the subnet.
+        let _ = sys.run(&["pkexec", "ip", "addr", "flush", "dev", interface]);
+        spawn_result(sys, &["pkexec", "ip", "addr", "add", HOSTAPD_CIDR, "dev", interface])?;
+        spawn_result(sys, &["pkexec", "ip", "link", "set", interface, "up"])?;
+
+        // Hand out leases (these feed `get_connected_devices`' lease lookups).
+        spawn_result(sys, &[
+            "pkexec", "dnsmasq",
+            &format!("--interface={}", interface),
+            "--bind-interfaces",
+            &format!("--listen-address={}", HOSTAPD_GATEWAY),
+            &format!("--dhcp-range={}", HOSTAPD_DHCP_RANGE),
+            "--dhcp-leasefile=/tmp/dnsmasq.leases",
+            &format!("--pid-file={}", DNSMASQ_PID),
+        ])?;
+
+        // Route and NAT the client subnet out to the internet.
+        spawn_result(sys, &["pkexec", "sysctl", "-w", "net.ipv4.ip_forward=1"])?;
+        spawn_result(sys, &[
+            "pkexec", "iptables", "-t", "nat", "-A", "POSTROUTING",
+            "-s", HOSTAPD_SUBNET, "-j", "MASQUERADE",
+        ])?;
+
+        // Run hostapd in the background with a pidfile so `stop` can target
+        // only our instance rather than every hostapd on the host.
+        spawn_result(sys, &["pkexec", "hostapd", "-B", "-P", HOSTAPD_PID, HOSTAPD_CONF])
+    }
+    fn stop(&self, sys: &dyn SystemBackend, interface: &str) -> Result<(), HotspotError> {
+        // Kill only the processes idkspot started, by the PIDs recorded in
+        // their pidfiles (read here so no shell is needed to expand them).
+        for pidfile in [HOSTAPD_PID, DNSMASQ_PID] {
+            if let Ok(contents) = std::fs::read_to_string(pidfile) {
+                if let Some(pid) = contents.split_whitespace().next() {
+                    let _ = sys.run(&["pkexec", "kill", pid]);
+                }
+            }
+        }
+        // Tear down the NAT rule and the gateway address.
+        let _ = sys.run(&[
+            "pkexec", "iptables", "-t", "nat", "-D", "POSTROUTING",
+            "-s", HOSTAPD_SUBNET, "-j", "MASQUERADE",
+        ]);
+        let _ = sys.run(&["pkexec", "ip", "addr", "flush", "dev", interface]);
+        Ok(())
+    }
+}
+
+/// Pick the first usable hotspot backend by probing `$PATH`, or honour an
+/// explicit override (`create_ap` / `nmcli` / `hostapd`). Resolving binaries
+/// with the `which` crate avoids assuming a tool exists just because a distro
+/// usually ships it.
+fn detect_hotspot_backend(force: Option<&str>) -> Arc<dyn HotspotBackend> {
+    let have = |bin: &str| which::which(bin).is_ok();
+    match force {
+        Some("create_ap") => return Arc::new(CreateApBackend),
+        Some("nmcli") => return Arc::new(NmcliBackend),
+        Some("hostapd") => return Arc::new(HostapdBackend),
+        _ => {}
+    }
+    if have("create_ap") {
+        Arc::new(CreateApBackend)
+    } else if have("nmcli") {
+        Arc::new(NmcliBackend)
+    } else if have("hostapd") && have("dnsmasq") {
+        Arc::new(HostapdBackend)
+    } else {
+        // Nothing detected; default to create_ap so the error surfaces at start.
+        Arc::new(CreateApBackend)
+    }
+}
+
+fn start_hotspot(sys: Arc<dyn SystemBackend>, hotspot: Arc<dyn HotspotBackend>, interface: &str, channel: u32, ssid: &str, password: &str) -> Result<String, HotspotError> {
+    validate_config(ssid, password)?;
     let interface = interface.to_string();
-    let channel_str = channel.to_string();
     let ssid_display = ssid.to_string();
     let ssid = ssid.to_string();
     let password = password.to_string();
+
+    // Re-apply any persisted per-client limits once the AP and DHCP have had
+    // time to come up.
+    let sys_limits = sys.clone();
+    let iface_limits = interface.clone();
     std::thread::spawn(move || {
-        let _ = Command::new("pkexec").args(["create_ap", "-c", &channel_str, &interface, &interface, &ssid, &password]).spawn();
+        std::thread::sleep(std::time::Duration::from_secs(5));
+        apply_saved_limits(sys_limits.as_ref(), &iface_limits);
     });
-    Ok(format!("Hotspot '{}' starting...", ssid_display))
+
+    // The AP tool typically stays in the foreground for the life of the
+    // hotspot, so run it on a detached thread. Send the outcome back over a
+    // channel and wait briefly: a backend that fails fast (missing binary, bad
+    // config, immediate non-zero exit) surfaces its error to the caller, while
+    // one still running after the window is reported as coming up.
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let result = hotspot.start(sys.as_ref(), &interface, channel, &ssid, &password);
+        if let Err(ref e) = result {
+            eprintln!("idkspot: hotspot failed: {}", e);
+        }
+        let _ = tx.send(result);
+    });
+    match rx.recv_timeout(std::time::Duration::from_millis(800)) {
+        Ok(Err(e)) => Err(e),
+        _ => Ok(format!("Hotspot '{}' starting...", ssid_display)),
+    }
+}
+
+fn stop_hotspot(sys: &dyn SystemBackend, hotspot: &dyn HotspotBackend, interface: &str) -> Result<(), HotspotError> {
+    hotspot.stop(sys, interface)
 }
 
-fn stop_hotspot(interface: &str) -> String {
-    match Command::new("pkexec").args(["create_ap", "--stop", interface]).spawn() {
-        Ok(_) => format!("Stopped on {}", interface), Err(e) => format!("Error: {}", e),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+
+    /// In-memory backend returning canned command output and file contents,
+    /// keyed by the space-joined argv / path.
+    #[derive(Default)]
+    struct MockBackend {
+        outputs: HashMap<String, String>,
+        files: HashMap<String, String>,
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl MockBackend {
+        fn with_output(mut self, argv: &str, stdout: &str) -> Self {
+            self.outputs.insert(argv.to_string(), stdout.to_string());
+            self
+        }
+
+        fn with_file(mut self, path: &str, content: &str) -> Self {
+            self.files.insert(path.to_string(), content.to_string());
+            self
+        }
+
+        fn calls(&self) -> Vec<String> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    impl SystemBackend for MockBackend {
+        fn run(&self, argv: &[&str]) -> std::io::Result<Output> {
+            self.calls.lock().unwrap().push(argv.join(" "));
+            let stdout = self.outputs.get(&argv.join(" ")).cloned().unwrap_or_default();
+            Ok(Output {
+                status: ExitStatus::from_raw(0),
+                stdout: stdout.into_bytes(),
+                stderr: Vec::new(),
+            })
+        }
+
+        fn run_root(&self, _cmd: &str) -> bool {
+            true
+        }
+
+        fn read_file(&self, path: &str) -> std::io::Result<String> {
+            self.files
+                .get(path)
+                .cloned()
+                .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "no such file"))
+        }
+    }
+
+    #[test]
+    fn freq_to_channel_maps_table_and_fallback() {
+        assert_eq!(freq_to_channel(2412), 1);
+        assert_eq!(freq_to_channel(2437), 6);
+        assert_eq!(freq_to_channel(2462), 11);
+        assert_eq!(freq_to_channel(2484), 14);
+        assert_eq!(freq_to_channel(5180), 36);
+        assert_eq!(freq_to_channel(5825), 165);
+        assert_eq!(freq_to_channel(9999), 0);
+    }
+
+    #[test]
+    fn check_compatibility_detects_ap_managed_combination() {
+        let iw_list = "\
+Wiphy phy0
+\tvalid interface combinations:
+\t\t * #{ managed } <= 1, #{ AP, mesh point } <= 1,
+\t\t   total <= 2, #channels <= 1
+Supported commands:";
+        let backend = MockBackend::default().with_output("iw list", iw_list);
+        let (ok, _msg) = check_compatibility(&backend);
+        assert!(ok);
+
+        let backend = MockBackend::default().with_output("iw list", "no combinations here");
+        let (ok, _msg) = check_compatibility(&backend);
+        assert!(!ok);
+    }
+
+    #[test]
+    fn detect_interface_parses_name_and_frequency() {
+        let iw_dev = "\
+phy#0
+\tInterface wlan0
+\t\ttype managed
+\t\tchannel 6 (2437 MHz), width: 20 MHz";
+        let backend = MockBackend::default().with_output("iw dev", iw_dev);
+        let (iface, freq, err) = detect_interface(&backend);
+        assert_eq!(iface, "wlan0");
+        assert_eq!(freq, 2437);
+        assert!(err.is_none());
+    }
+
+    #[test]
+    fn is_valid_mac_accepts_only_well_formed_addresses() {
+        assert!(is_valid_mac("aa:bb:cc:dd:ee:ff"));
+        assert!(is_valid_mac("AA:BB:CC:DD:EE:FF"));
+        assert!(!is_valid_mac("aa:bb:cc:dd:ee")); // too short
+        assert!(!is_valid_mac("aa:bb:cc:dd:ee:ff; rm -rf /")); // injection attempt
+        assert!(!is_valid_mac(""));
+    }
+
+    #[test]
+    fn handle_root_verb_rejects_bad_input_without_shelling_out() {
+        assert_eq!(handle_root_verb("BLOCK_MAC not-a-mac"), "ERR invalid MAC");
+        assert_eq!(handle_root_verb("BLOCK_MAC"), "ERR missing MAC");
+        assert_eq!(handle_root_verb(""), "ERR empty command");
+        assert_eq!(handle_root_verb("NUKE everything"), "ERR unknown verb NUKE");
+    }
+
+    #[test]
+    fn parse_scan_pairs_freq_with_signal() {
+        let scan = "\
+BSS 00:11:22:33:44:55(on wlan0)
+\tfreq: 2412
+\tsignal: -40.00 dBm
+BSS 66:77:88:99:aa:bb(on wlan0)
+\tfreq: 2462
+\tsignal: -70.00 dBm";
+        let neighbors = parse_scan(scan);
+        assert_eq!(neighbors, vec![(2412, -40.0), (2462, -70.0)]);
+    }
+
+    #[test]
+    fn recommend_channels_avoids_the_crowded_channel() {
+        // Two strong APs on channel 1, nothing on 6/11 -> recommend away from 1.
+        let neighbors = vec![(2412, -30.0), (2412, -35.0)];
+        let scan = recommend_channels(&neighbors);
+        assert_ne!(scan.recommended_24, 1);
+        assert!(CANDIDATES_24.contains(&scan.recommended_24));
+        // No 5 GHz neighbours seen -> no 5 GHz recommendation.
+        assert!(scan.recommended_5.is_none());
+    }
+
+    #[test]
+    fn get_connected_devices_joins_station_dump_with_leases() {
+        let station_dump = "\
+Station aa:bb:cc:dd:ee:ff (on wlan0)
+\trx bytes: 1024
+\ttx bytes: 2048";
+        let leases = "1700000000 aa:bb:cc:dd:ee:ff 192.168.12.34 myphone *";
+        let backend = MockBackend::default()
+            .with_output("iw dev wlan0 station dump", station_dump)
+            .with_file("/var/lib/misc/dnsmasq.leases", leases);
+        let devices = get_connected_devices(&backend, "wlan0");
+        assert_eq!(
+            devices,
+            vec![Device {
+                mac: "AA:BB:CC:DD:EE:FF".to_string(),
+                hostname: "myphone".to_string(),
+                rx_bytes: 1024,
+                tx_bytes: 2048,
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_station_dump_reads_byte_counters() {
+        let dump = "\
+Station aa:bb:cc:dd:ee:ff (on wlan0)
+\trx bytes:\t4096
+\ttx bytes:\t8192
+Station 11:22:33:44:55:66 (on wlan0)
+\trx bytes:\t10";
+        let stations = parse_station_dump(dump);
+        assert_eq!(
+            stations,
+            vec![
+                ("AA:BB:CC:DD:EE:FF".to_string(), 4096, 8192),
+                ("11:22:33:44:55:66".to_string(), 10, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn ip_classid_uses_last_octet() {
+        assert_eq!(ip_classid("192.168.12.34"), 34);
+        assert_eq!(ip_classid("10.0.0.255"), 1); // out of 1..=254 -> fallback
+    }
+
+    #[test]
+    fn forced_hotspot_backend_is_honoured() {
+        assert_eq!(detect_hotspot_backend(Some("nmcli")).name(), "nmcli");
+        assert_eq!(detect_hotspot_backend(Some("hostapd")).name(), "hostapd");
+        assert_eq!(detect_hotspot_backend(Some("create_ap")).name(), "create_ap");
+    }
+
+    #[test]
+    fn nmcli_backend_issues_expected_command() {
+        let backend = MockBackend::default();
+        NmcliBackend.start(&backend, "wlan0", 6, "idkspot", "secretpw").unwrap();
+        assert_eq!(
+            backend.calls(),
+            vec!["pkexec nmcli device wifi hotspot ifname wlan0 ssid idkspot password secretpw".to_string()]
+        );
     }
 }